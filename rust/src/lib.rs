@@ -6,9 +6,16 @@ use std::os::unix::ffi::OsStrExt;
 use std::path::Path;
 use std::slice;
 use zcash_client_backend::{
-    constants::{testnet::HRP_SAPLING_EXTENDED_SPENDING_KEY},
-    encoding::{decode_extended_spending_key, encode_extended_spending_key},
-    keys::spending_key,
+    constants::{mainnet, testnet},
+    encoding::{
+        decode_extended_full_viewing_key, decode_extended_spending_key, decode_payment_address,
+        decode_transparent_address, encode_extended_full_viewing_key,
+        encode_extended_spending_key, encode_payment_address, encode_transparent_address,
+    },
+    keys::{
+        derive_secret_key_from_seed, derive_transparent_address_from_public_key,
+        derive_transparent_address_from_secret_key, spending_key,
+    },
 };
 use zcash_client_sqlite::{
     address::RecipientAddress,
@@ -20,10 +27,21 @@ use zcash_client_sqlite::{
         get_verified_balance,
     },
     scan::scan_cached_blocks,
-    transact::create_to_address,
+    transact::{create_to_address, decrypt_and_store_transaction, shield_funds},
+    wallet::{delete_utxos_above, put_received_transparent_utxo, WalletTransparentOutput},
 };
+use rusqlite::{Connection, NO_PARAMS};
+use secp256k1::{PublicKey, SecretKey};
 use zcash_primitives::{
-    block::BlockHash, note_encryption::Memo, transaction::components::Amount,
+    block::BlockHash,
+    consensus::{BlockHeight, BranchId, MainNetwork, Parameters, TestNetwork},
+    legacy::Script,
+    note_encryption::Memo,
+    transaction::{
+        builder::OvkPolicy,
+        components::{Amount, OutPoint, TxOut},
+        Transaction,
+    },
     zip32::ExtendedFullViewingKey,
 };
 use zcash_proofs::prover::LocalTxProver;
@@ -45,6 +63,175 @@ where
     }
 }
 
+/// The network that a `zcashlc_*` call is operating against.
+///
+/// FFI entry points that encode or decode keys and addresses take a `network: u32`
+/// argument (`0` for Testnet, `1` for Mainnet) and resolve it to this type, so that a
+/// single compiled library can serve wallets on either network.
+#[derive(Clone, Copy)]
+enum Network {
+    Testnet,
+    Mainnet,
+}
+
+impl Network {
+    fn from_u32(network: u32) -> Result<Self, failure::Error> {
+        match network {
+            0 => Ok(Network::Testnet),
+            1 => Ok(Network::Mainnet),
+            _ => Err(format_err!(
+                "Invalid network argument: {}. Expected 0 (testnet) or 1 (mainnet).",
+                network
+            )),
+        }
+    }
+
+    /// The BIP 44 coin type to use when deriving keys for this network.
+    fn coin_type(&self) -> u32 {
+        match self {
+            Network::Testnet => 1,
+            Network::Mainnet => 133,
+        }
+    }
+
+    fn hrp_sapling_extended_spending_key(&self) -> &'static str {
+        match self {
+            Network::Testnet => testnet::HRP_SAPLING_EXTENDED_SPENDING_KEY,
+            Network::Mainnet => mainnet::HRP_SAPLING_EXTENDED_SPENDING_KEY,
+        }
+    }
+
+    fn hrp_sapling_extended_full_viewing_key(&self) -> &'static str {
+        match self {
+            Network::Testnet => testnet::HRP_SAPLING_EXTENDED_FULL_VIEWING_KEY,
+            Network::Mainnet => mainnet::HRP_SAPLING_EXTENDED_FULL_VIEWING_KEY,
+        }
+    }
+
+    fn hrp_sapling_payment_address(&self) -> &'static str {
+        match self {
+            Network::Testnet => testnet::HRP_SAPLING_PAYMENT_ADDRESS,
+            Network::Mainnet => mainnet::HRP_SAPLING_PAYMENT_ADDRESS,
+        }
+    }
+
+    fn b58_pubkey_address_prefix(&self) -> [u8; 2] {
+        match self {
+            Network::Testnet => testnet::B58_PUBKEY_ADDRESS_PREFIX,
+            Network::Mainnet => mainnet::B58_PUBKEY_ADDRESS_PREFIX,
+        }
+    }
+
+    fn b58_script_address_prefix(&self) -> [u8; 2] {
+        match self {
+            Network::Testnet => testnet::B58_SCRIPT_ADDRESS_PREFIX,
+            Network::Mainnet => mainnet::B58_SCRIPT_ADDRESS_PREFIX,
+        }
+    }
+
+    /// The WIF version byte used to encode transparent secret keys for this network.
+    fn wif_prefix(&self) -> u8 {
+        match self {
+            Network::Testnet => 0xef,
+            Network::Mainnet => 0x80,
+        }
+    }
+}
+
+/// Decodes a shielded or transparent address string using the HRP/prefixes for `network`.
+///
+/// This exists because [`RecipientAddress::from_str`] only recognises Testnet-encoded
+/// addresses; callers that need to support both networks from a single build go through
+/// this instead.
+fn decode_address(network: Network, s: &str) -> Option<RecipientAddress> {
+    if let Ok(Some(addr)) = decode_payment_address(network.hrp_sapling_payment_address(), s) {
+        return Some(RecipientAddress::Shielded(addr));
+    }
+    if let Ok(Some(addr)) = decode_transparent_address(
+        &network.b58_pubkey_address_prefix(),
+        &network.b58_script_address_prefix(),
+        s,
+    ) {
+        return Some(RecipientAddress::Transparent(addr));
+    }
+    None
+}
+
+/// Returns the height at which a transaction created right now would be mined: one block
+/// above the highest block the wallet has scanned.
+///
+/// Only the upper end of the block extrema is read (`SELECT MAX(height) FROM blocks`);
+/// the lowest scanned height is not needed here.
+fn get_target_height(db_data: &Path) -> Result<BlockHeight, failure::Error> {
+    let conn = Connection::open(db_data)?;
+    let max_height: i64 = conn.query_row(
+        "SELECT MAX(height) FROM blocks",
+        NO_PARAMS,
+        |row| row.get(0),
+    )?;
+    Ok(BlockHeight::from_u32((max_height + 1) as u32))
+}
+
+/// Looks up the consensus branch ID active at `height` on `network`, by consulting the
+/// network's activation-height table. This keeps transaction construction working across
+/// network upgrades (Heartwood, Canopy, NU5, ...) without rebuilding the library.
+fn branch_id_for_height(network: Network, height: BlockHeight) -> BranchId {
+    match network {
+        Network::Testnet => BranchId::for_height(&TestNetwork, height),
+        Network::Mainnet => BranchId::for_height(&MainNetwork, height),
+    }
+}
+
+/// Resolves the `ovk_policy` FFI argument (`0` = sender, `1` = custom, `2` = none) to an
+/// [`OvkPolicy`].
+///
+/// `1` (custom) is rejected for now: a custom outgoing viewing key has no representation
+/// in this API yet, so there is nothing to select it with.
+fn ovk_policy_from_u32(policy: u32) -> Result<OvkPolicy, failure::Error> {
+    match policy {
+        0 => Ok(OvkPolicy::Sender),
+        1 => Err(format_err!(
+            "Custom OVK policy requires an explicit outgoing viewing key, which this API does not yet accept"
+        )),
+        2 => Ok(OvkPolicy::Discard),
+        _ => Err(format_err!(
+            "Invalid ovk_policy argument: {}. Expected 0 (sender), 1 (custom), or 2 (none).",
+            policy
+        )),
+    }
+}
+
+/// Decodes a WIF (Wallet Import Format) string into the secp256k1 secret key it encodes.
+fn decode_wif_to_sk(network: Network, wif: &str) -> Result<SecretKey, failure::Error> {
+    let data = bs58::decode(wif)
+        .with_check(None)
+        .into_vec()
+        .map_err(|e| format_err!("Invalid WIF-encoded secret key: {}", e))?;
+
+    // data[0] is the version byte; an optional trailing 0x01 marks a compressed pubkey.
+    let key_bytes = match data.len() {
+        33 | 34 => &data[1..33],
+        _ => return Err(format_err!("Invalid WIF-encoded secret key length")),
+    };
+
+    if data[0] != network.wif_prefix() {
+        return Err(format_err!(
+            "WIF-encoded secret key is for the wrong network"
+        ));
+    }
+
+    SecretKey::from_slice(key_bytes).map_err(|e| format_err!("Invalid secret key: {}", e))
+}
+
+/// Encodes a secp256k1 secret key as a WIF (Wallet Import Format) string, for a
+/// compressed public key, on `network`.
+fn encode_wif(network: Network, sk: &SecretKey) -> String {
+    let mut data = vec![network.wif_prefix()];
+    data.extend_from_slice(&sk[..]);
+    data.push(0x01);
+    bs58::encode(data).with_check().into_string()
+}
+
 /// Returns the length of the last error message to be logged.
 #[no_mangle]
 pub extern "C" fn zcashlc_last_error_length() -> i32 {
@@ -88,6 +275,7 @@ pub extern "C" fn zcashlc_init_data_database(db_data: *const u8, db_data_len: us
 pub extern "C" fn zcashlc_init_accounts_table(
     db_data: *const u8,
     db_data_len: usize,
+    network: u32,
     seed: *const u8,
     seed_len: usize,
     accounts: i32,
@@ -96,6 +284,7 @@ pub extern "C" fn zcashlc_init_accounts_table(
         let db_data = Path::new(OsStr::from_bytes(unsafe {
             slice::from_raw_parts(db_data, db_data_len)
         }));
+        let network = Network::from_u32(network)?;
         let seed = unsafe { slice::from_raw_parts(seed, seed_len) };
         let accounts = if accounts >= 0 {
             accounts as u32
@@ -104,7 +293,7 @@ pub extern "C" fn zcashlc_init_accounts_table(
         };
 
         let extsks: Vec<_> = (0..accounts)
-            .map(|account| spending_key(&seed, 1, account))
+            .map(|account| spending_key(&seed, network.coin_type(), account))
             .collect();
         let extfvks: Vec<_> = extsks.iter().map(ExtendedFullViewingKey::from).collect();
 
@@ -123,7 +312,7 @@ pub extern "C" fn zcashlc_init_accounts_table(
             .iter()
             .map(|extsk| {
                 let encoded =
-                    encode_extended_spending_key(HRP_SAPLING_EXTENDED_SPENDING_KEY, extsk);
+                    encode_extended_spending_key(network.hrp_sapling_extended_spending_key(), extsk);
                 CString::new(encoded).unwrap().into_raw()
             })
             .collect();
@@ -135,6 +324,225 @@ pub extern "C" fn zcashlc_init_accounts_table(
     unwrap_exc_or_null(res)
 }
 
+/// Derives the encoded ExtendedSpendingKey for the given seed and account index, without
+/// touching the data database.
+///
+/// Call `zcashlc_string_free` on the returned pointer when you are finished with it.
+#[no_mangle]
+pub extern "C" fn zcashlc_derive_extended_spending_key(
+    seed: *const u8,
+    seed_len: usize,
+    account: i32,
+    network: u32,
+) -> *mut c_char {
+    let res = catch_panic(|| {
+        let network = Network::from_u32(network)?;
+        let seed = unsafe { slice::from_raw_parts(seed, seed_len) };
+        let account = if account >= 0 {
+            account as u32
+        } else {
+            return Err(format_err!("account argument must be positive"));
+        };
+
+        let extsk = spending_key(&seed, network.coin_type(), account);
+        let encoded = encode_extended_spending_key(network.hrp_sapling_extended_spending_key(), &extsk);
+        Ok(CString::new(encoded).unwrap().into_raw())
+    });
+    unwrap_exc_or_null(res)
+}
+
+/// Derives the encoded ExtendedFullViewingKey for the given encoded ExtendedSpendingKey.
+///
+/// Call `zcashlc_string_free` on the returned pointer when you are finished with it.
+#[no_mangle]
+pub extern "C" fn zcashlc_derive_extended_full_viewing_key(
+    extsk: *const c_char,
+    network: u32,
+) -> *mut c_char {
+    let res = catch_panic(|| {
+        let network = Network::from_u32(network)?;
+        let extsk = unsafe { CStr::from_ptr(extsk) }.to_str()?;
+
+        let extsk = match decode_extended_spending_key(network.hrp_sapling_extended_spending_key(), &extsk) {
+            Ok(Some(extsk)) => extsk,
+            Ok(None) => return Err(format_err!("ExtendedSpendingKey is for the wrong network")),
+            Err(e) => return Err(format_err!("Invalid ExtendedSpendingKey: {}", e)),
+        };
+
+        let extfvk = ExtendedFullViewingKey::from(&extsk);
+        let encoded = encode_extended_full_viewing_key(
+            network.hrp_sapling_extended_full_viewing_key(),
+            &extfvk,
+        );
+        Ok(CString::new(encoded).unwrap().into_raw())
+    });
+    unwrap_exc_or_null(res)
+}
+
+/// Derives the shielded payment address for the given encoded ExtendedFullViewingKey.
+///
+/// Call `zcashlc_string_free` on the returned pointer when you are finished with it.
+#[no_mangle]
+pub extern "C" fn zcashlc_derive_shielded_address_from_viewing_key(
+    extfvk: *const c_char,
+    network: u32,
+) -> *mut c_char {
+    let res = catch_panic(|| {
+        let network = Network::from_u32(network)?;
+        let extfvk = unsafe { CStr::from_ptr(extfvk) }.to_str()?;
+
+        let extfvk = match decode_extended_full_viewing_key(
+            network.hrp_sapling_extended_full_viewing_key(),
+            &extfvk,
+        ) {
+            Ok(Some(extfvk)) => extfvk,
+            Ok(None) => {
+                return Err(format_err!(
+                    "ExtendedFullViewingKey is for the wrong network"
+                ))
+            }
+            Err(e) => return Err(format_err!("Invalid ExtendedFullViewingKey: {}", e)),
+        };
+
+        let address = extfvk.default_address().map_err(|_| {
+            format_err!("Unable to derive a default shielded address for this viewing key")
+        })?;
+        let encoded = encode_payment_address(network.hrp_sapling_payment_address(), &address.1);
+        Ok(CString::new(encoded).unwrap().into_raw())
+    });
+    unwrap_exc_or_null(res)
+}
+
+/// Derives a WIF-encoded transparent private key for the given seed and account index.
+///
+/// Call `zcashlc_string_free` on the returned pointer when you are finished with it.
+#[no_mangle]
+pub extern "C" fn zcashlc_derive_transparent_private_key_from_seed(
+    seed: *const u8,
+    seed_len: usize,
+    account: i32,
+    network: u32,
+) -> *mut c_char {
+    let res = catch_panic(|| {
+        let network = Network::from_u32(network)?;
+        let seed = unsafe { slice::from_raw_parts(seed, seed_len) };
+        let account = if account >= 0 {
+            account as u32
+        } else {
+            return Err(format_err!("account argument must be positive"));
+        };
+
+        let sk = derive_secret_key_from_seed(network.coin_type(), &seed, account, 0)
+            .ok_or_else(|| format_err!("Unable to derive transparent private key"))?;
+
+        Ok(CString::new(encode_wif(network, &sk)).unwrap().into_raw())
+    });
+    unwrap_exc_or_null(res)
+}
+
+/// Derives the transparent address for the given WIF-encoded secret key.
+///
+/// Call `zcashlc_string_free` on the returned pointer when you are finished with it.
+#[no_mangle]
+pub extern "C" fn zcashlc_derive_transparent_address_from_secret_key(
+    tsk: *const c_char,
+    network: u32,
+) -> *mut c_char {
+    let res = catch_panic(|| {
+        let network = Network::from_u32(network)?;
+        let tsk = unsafe { CStr::from_ptr(tsk) }.to_str()?;
+        let sk = decode_wif_to_sk(network, &tsk)?;
+
+        let taddr = derive_transparent_address_from_secret_key(&sk);
+        let encoded = encode_transparent_address(
+            &network.b58_pubkey_address_prefix(),
+            &network.b58_script_address_prefix(),
+            &taddr,
+        );
+        Ok(CString::new(encoded).unwrap().into_raw())
+    });
+    unwrap_exc_or_null(res)
+}
+
+/// Derives the transparent address for the given compressed secp256k1 public key.
+///
+/// Call `zcashlc_string_free` on the returned pointer when you are finished with it.
+#[no_mangle]
+pub extern "C" fn zcashlc_derive_transparent_address_from_public_key(
+    pubkey: *const u8,
+    pubkey_len: usize,
+    network: u32,
+) -> *mut c_char {
+    let res = catch_panic(|| {
+        let network = Network::from_u32(network)?;
+        let pubkey = unsafe { slice::from_raw_parts(pubkey, pubkey_len) };
+        let pubkey = PublicKey::from_slice(pubkey)
+            .map_err(|e| format_err!("Invalid public key: {}", e))?;
+
+        let taddr = derive_transparent_address_from_public_key(&pubkey);
+        let encoded = encode_transparent_address(
+            &network.b58_pubkey_address_prefix(),
+            &network.b58_script_address_prefix(),
+            &taddr,
+        );
+        Ok(CString::new(encoded).unwrap().into_raw())
+    });
+    unwrap_exc_or_null(res)
+}
+
+/// Initialises the data database with one account for each of the given encoded
+/// ExtendedFullViewingKeys, rather than from a seed.
+///
+/// This allows view-only and hardware-wallet setups where the spending key never enters
+/// this process: the caller derives (or is handed) the viewing keys elsewhere and only
+/// passes their encoded string form here. Importing transparent-side view keys is not yet
+/// supported, since `init_accounts_table` only tracks the Sapling viewing key per account.
+///
+/// As with `zcashlc_init_accounts_table`, a `TableNotEmpty` error from a prior call is
+/// treated as success rather than an error.
+#[no_mangle]
+pub extern "C" fn zcashlc_init_accounts_table_with_keys(
+    db_data: *const u8,
+    db_data_len: usize,
+    network: u32,
+    extfvks: *const *const c_char,
+    extfvks_len: usize,
+) -> i32 {
+    let res = catch_panic(|| {
+        let db_data = Path::new(OsStr::from_bytes(unsafe {
+            slice::from_raw_parts(db_data, db_data_len)
+        }));
+        let network = Network::from_u32(network)?;
+        let extfvks = unsafe { slice::from_raw_parts(extfvks, extfvks_len) };
+
+        let extfvks: Vec<_> = extfvks
+            .iter()
+            .map(|&s| {
+                let s = unsafe { CStr::from_ptr(s) }.to_str()?;
+                match decode_extended_full_viewing_key(
+                    network.hrp_sapling_extended_full_viewing_key(),
+                    s,
+                ) {
+                    Ok(Some(extfvk)) => Ok(extfvk),
+                    Ok(None) => Err(format_err!(
+                        "ExtendedFullViewingKey is for the wrong network"
+                    )),
+                    Err(e) => Err(format_err!("Invalid ExtendedFullViewingKey: {}", e)),
+                }
+            })
+            .collect::<Result<_, failure::Error>>()?;
+
+        match init_accounts_table(&db_data, &extfvks) {
+            Ok(()) => Ok(1),
+            Err(e) => match e.kind() {
+                ErrorKind::TableNotEmpty => Ok(1),
+                _ => Err(format_err!("Error while initializing accounts: {}", e)),
+            },
+        }
+    });
+    unwrap_exc_or_null(res)
+}
+
 /// Initialises the data database with the given block.
 ///
 /// This enables a newly-created database to be immediately-usable, without needing to
@@ -170,17 +578,24 @@ pub extern "C" fn zcashlc_init_blocks_table(
 
 /// Returns the address for the account.
 ///
+/// `network` must match the network (`0` = testnet, `1` = mainnet) that the account's
+/// address was encoded for at `zcashlc_init_accounts_table` time. It is only checked to be
+/// a known network value; it is not compared against the stored address, which is read
+/// back verbatim from the data database.
+///
 /// Call `zcashlc_string_free` on the returned pointer when you are finished with it.
 #[no_mangle]
 pub extern "C" fn zcashlc_get_address(
     db_data: *const u8,
     db_data_len: usize,
+    network: u32,
     account: i32,
 ) -> *mut c_char {
     let res = catch_panic(|| {
         let db_data = Path::new(OsStr::from_bytes(unsafe {
             slice::from_raw_parts(db_data, db_data_len)
         }));
+        let _network = Network::from_u32(network)?;
         let account = if account >= 0 {
             account as u32
         } else {
@@ -384,12 +799,19 @@ pub extern "C" fn zcashlc_rewind_to_height(
 ///
 /// Scanned blocks are required to be height-sequential. If a block is missing from the
 /// cache, an error will be signalled.
+///
+/// `limit` bounds the number of blocks scanned by this call, starting just above the
+/// highest already-scanned block; pass `0` to scan every unscanned block in one call. Each
+/// batch is committed to `db_data` as it completes, so callers can loop on this function
+/// between batches (to report progress, or yield to other work) and safely resume from
+/// where an interrupted scan left off.
 #[no_mangle]
 pub extern "C" fn zcashlc_scan_blocks(
     db_cache: *const u8,
     db_cache_len: usize,
     db_data: *const u8,
     db_data_len: usize,
+    limit: u32,
 ) -> i32 {
     let res = catch_panic(|| {
         let db_cache = Path::new(OsStr::from_bytes(unsafe {
@@ -398,8 +820,9 @@ pub extern "C" fn zcashlc_scan_blocks(
         let db_data = Path::new(OsStr::from_bytes(unsafe {
             slice::from_raw_parts(db_data, db_data_len)
         }));
+        let limit = if limit > 0 { Some(limit) } else { None };
 
-        match scan_cached_blocks(&db_cache, &db_data) {
+        match scan_cached_blocks(&db_cache, &db_data, limit) {
             Ok(()) => Ok(1),
             Err(e) => Err(format_err!("Error while scanning blocks: {}", e)),
         }
@@ -407,8 +830,49 @@ pub extern "C" fn zcashlc_scan_blocks(
     unwrap_exc_or_null(res)
 }
 
+/// Decrypts and stores a transaction that was learned about out-of-band, i.e. not via the
+/// scanned compact-block stream (for example, a just-broadcast transaction, or one fetched
+/// by txid from `lightwalletd`).
+///
+/// The transaction's Sapling outputs are trial-decrypted against every account's viewing
+/// key, and any recovered notes and sent/received memos are written to the
+/// `received_notes`/`sent_notes` tables, exactly as scanning a block containing this
+/// transaction would have done.
+#[no_mangle]
+pub extern "C" fn zcashlc_decrypt_and_store_transaction(
+    db_data: *const u8,
+    db_data_len: usize,
+    tx: *const u8,
+    tx_len: usize,
+) -> i32 {
+    let res = catch_panic(|| {
+        let db_data = Path::new(OsStr::from_bytes(unsafe {
+            slice::from_raw_parts(db_data, db_data_len)
+        }));
+        let tx_bytes = unsafe { slice::from_raw_parts(tx, tx_len) };
+        let tx = Transaction::read(tx_bytes).map_err(|e| format_err!("Invalid transaction: {}", e))?;
+
+        match decrypt_and_store_transaction(&db_data, &tx) {
+            Ok(()) => Ok(1),
+            Err(e) => Err(format_err!(
+                "Error while decrypting and storing transaction: {}",
+                e
+            )),
+        }
+    });
+    unwrap_exc_or_null(res)
+}
+
 /// Creates a transaction paying the specified address from the given account.
 ///
+/// The consensus branch ID is derived from the target height (one block above the
+/// wallet's highest scanned block), so this keeps working across network upgrades without
+/// requiring a new build of the library.
+///
+/// `ovk_policy` controls whether the sender's outgoing viewing key is attached to the
+/// transaction's outputs (see [`ovk_policy_from_u32`]); pass `0` to preserve the previous
+/// behavior of always attaching it.
+///
 /// Returns the row index of the newly-created transaction in the `transactions` table
 /// within the data database. The caller can read the raw transaction bytes from the `raw`
 /// column in order to broadcast the transaction to the network.
@@ -419,6 +883,7 @@ pub extern "C" fn zcashlc_scan_blocks(
 pub extern "C" fn zcashlc_create_to_address(
     db_data: *const u8,
     db_data_len: usize,
+    network: u32,
     account: i32,
     extsk: *const c_char,
     to: *const c_char,
@@ -428,11 +893,14 @@ pub extern "C" fn zcashlc_create_to_address(
     spend_params_len: usize,
     output_params: *const u8,
     output_params_len: usize,
+    ovk_policy: u32,
 ) -> i64 {
     let res = catch_panic(|| {
         let db_data = Path::new(OsStr::from_bytes(unsafe {
             slice::from_raw_parts(db_data, db_data_len)
         }));
+        let network = Network::from_u32(network)?;
+        let ovk_policy = ovk_policy_from_u32(ovk_policy)?;
         let account = if account >= 0 {
             account as u32
         } else {
@@ -453,7 +921,10 @@ pub extern "C" fn zcashlc_create_to_address(
             slice::from_raw_parts(output_params, output_params_len)
         }));
 
-        let extsk = match decode_extended_spending_key(HRP_SAPLING_EXTENDED_SPENDING_KEY, &extsk) {
+        let extsk = match decode_extended_spending_key(
+            network.hrp_sapling_extended_spending_key(),
+            &extsk,
+        ) {
             Ok(Some(extsk)) => extsk,
             Ok(None) => {
                 return Err(format_err!("ExtendedSpendingKey is for the wrong network"));
@@ -463,7 +934,7 @@ pub extern "C" fn zcashlc_create_to_address(
             }
         };
 
-        let to = match RecipientAddress::from_str(&to) {
+        let to = match decode_address(network, &to) {
             Some(to) => to,
             None => {
                 return Err(format_err!("PaymentAddress is for the wrong network"));
@@ -473,21 +944,178 @@ pub extern "C" fn zcashlc_create_to_address(
         let memo = Memo::from_str(&memo);
 
         let prover = LocalTxProver::new(spend_params, output_params);
-        
+
+        let target_height = get_target_height(&db_data)?;
+        let branch_id = branch_id_for_height(network, target_height);
+
         create_to_address(
             &db_data,
-            0x2bb4_0e60, // BLOSSOM_CONSENSUS_BRANCH_ID
+            u32::from(branch_id),
             prover,
             (account, &extsk),
             &to,
             value,
             memo,
+            ovk_policy,
         )
         .map_err(|e| format_err!("Error while sending funds: {}", e))
     });
     unwrap_exc_or(res, -1)
 }
 
+/// Records a transparent output (UTXO) received at a t-address so that it can later be
+/// spent or swept into the shielded pool with `zcashlc_shield_funds`.
+///
+/// Returns the row index of the newly-recorded UTXO in the `utxos` table, or `-1` on
+/// error.
+#[no_mangle]
+pub extern "C" fn zcashlc_put_utxo(
+    db_data: *const u8,
+    db_data_len: usize,
+    txid_bytes: *const u8,
+    txid_bytes_len: usize,
+    index: i32,
+    script: *const u8,
+    script_len: usize,
+    value: i64,
+    height: i32,
+) -> i64 {
+    let res = catch_panic(|| {
+        let db_data = Path::new(OsStr::from_bytes(unsafe {
+            slice::from_raw_parts(db_data, db_data_len)
+        }));
+        let txid_bytes = unsafe { slice::from_raw_parts(txid_bytes, txid_bytes_len) };
+        let mut txid = [0u8; 32];
+        if txid_bytes.len() != txid.len() {
+            return Err(format_err!("txid must be 32 bytes"));
+        }
+        txid.copy_from_slice(txid_bytes);
+        let index = if index >= 0 {
+            index as u32
+        } else {
+            return Err(format_err!("index argument must be positive"));
+        };
+        let script = unsafe { slice::from_raw_parts(script, script_len) }.to_vec();
+        let value =
+            Amount::from_i64(value).map_err(|()| format_err!("Invalid amount, out of range"))?;
+        let height = if height >= 0 {
+            BlockHeight::from_u32(height as u32)
+        } else {
+            return Err(format_err!("height argument must be positive"));
+        };
+
+        let output = WalletTransparentOutput {
+            outpoint: OutPoint::new(txid, index),
+            txout: TxOut {
+                value,
+                script_pubkey: Script(script),
+            },
+            height,
+        };
+
+        match put_received_transparent_utxo(&db_data, &output) {
+            Ok(id) => Ok(id),
+            Err(e) => Err(format_err!("Error while inserting UTXO: {}", e)),
+        }
+    });
+    unwrap_exc_or(res, -1)
+}
+
+/// Deletes all recorded UTXOs above the given height.
+///
+/// Call this when handling a reorg, before re-scanning from the rewound height, so that
+/// UTXOs belonging to discarded blocks aren't treated as spendable.
+#[no_mangle]
+pub extern "C" fn zcashlc_clear_utxos(
+    db_data: *const u8,
+    db_data_len: usize,
+    above_height: i32,
+) -> i32 {
+    let res = catch_panic(|| {
+        let db_data = Path::new(OsStr::from_bytes(unsafe {
+            slice::from_raw_parts(db_data, db_data_len)
+        }));
+        let above_height = if above_height >= 0 {
+            BlockHeight::from_u32(above_height as u32)
+        } else {
+            return Err(format_err!("above_height argument must be positive"));
+        };
+
+        match delete_utxos_above(&db_data, above_height) {
+            Ok(count) => Ok(count as i32),
+            Err(e) => Err(format_err!("Error while clearing UTXOs: {}", e)),
+        }
+    });
+    unwrap_exc_or_null(res)
+}
+
+/// Shields the transparent funds held by the given WIF-encoded secret key, sending them
+/// to the shielded address of `account`.
+///
+/// Returns the row index of the newly-created transaction in the `transactions` table
+/// within the data database, exactly like `zcashlc_create_to_address`.
+///
+/// Do not call this multiple times in parallel, or you will generate transactions that
+/// double-spend the same UTXOs.
+#[no_mangle]
+pub extern "C" fn zcashlc_shield_funds(
+    db_data: *const u8,
+    db_data_len: usize,
+    network: u32,
+    account: i32,
+    tsk: *const c_char,
+    memo: *const c_char,
+    spend_params: *const u8,
+    spend_params_len: usize,
+    output_params: *const u8,
+    output_params_len: usize,
+) -> i64 {
+    let res = catch_panic(|| {
+        let db_data = Path::new(OsStr::from_bytes(unsafe {
+            slice::from_raw_parts(db_data, db_data_len)
+        }));
+        let network = Network::from_u32(network)?;
+        let account = if account >= 0 {
+            account as u32
+        } else {
+            return Err(format_err!("account argument must be positive"));
+        };
+        let tsk = unsafe { CStr::from_ptr(tsk) }.to_str()?;
+        let memo = unsafe { CStr::from_ptr(memo) }.to_str()?;
+        let memo = Memo::from_str(&memo);
+        let spend_params = Path::new(OsStr::from_bytes(unsafe {
+            slice::from_raw_parts(spend_params, spend_params_len)
+        }));
+        let output_params = Path::new(OsStr::from_bytes(unsafe {
+            slice::from_raw_parts(output_params, output_params_len)
+        }));
+
+        let sk = decode_wif_to_sk(network, &tsk)?;
+
+        let to = match get_address(&db_data, account) {
+            Ok(addr) => decode_address(network, &addr)
+                .ok_or_else(|| format_err!("Stored shielded address is invalid"))?,
+            Err(e) => return Err(format_err!("Error while fetching shielding address: {}", e)),
+        };
+
+        let target_height = get_target_height(&db_data)?;
+        let branch_id = branch_id_for_height(network, target_height);
+        let prover = LocalTxProver::new(spend_params, output_params);
+
+        shield_funds(
+            &db_data,
+            u32::from(branch_id),
+            prover,
+            account,
+            &sk,
+            &to,
+            memo,
+        )
+        .map_err(|e| format_err!("Error while shielding funds: {}", e))
+    });
+    unwrap_exc_or(res, -1)
+}
+
 /// Frees strings returned by other zcashlc functions.
 #[no_mangle]
 pub extern "C" fn zcashlc_string_free(s: *mut c_char) {